@@ -34,7 +34,7 @@ impl From<Position> for ChunkId {
 
 pub type BlockId = u8;
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub struct Block(BlockId);
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -43,6 +43,84 @@ pub struct Chunk {
     blocks: Vec<Option<Block>>,
 }
 
+/// The number of vertical `(x, y)` block columns in a chunk.
+const COLUMN_COUNT: usize = Chunk::WIDTH * Chunk::WIDTH;
+
+/// Smallest number of bits that can represent `len` distinct palette
+/// entries (`0`/`1` entries still need 1 bit, since a block array is never
+/// actually empty).
+fn bits_for_palette_len(len: usize) -> u32 {
+    if len <= 1 {
+        1
+    } else {
+        (usize::BITS - (len - 1).leading_zeros()).max(1)
+    }
+}
+
+/// Appends values of a given bit width to a byte buffer, LSB-first within
+/// each byte.
+struct BitWriter {
+    bytes: Vec<u8>,
+    bit_pos: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            bit_pos: 0,
+        }
+    }
+
+    fn write(&mut self, value: u8, bits: u32) {
+        for i in 0..bits {
+            if self.bit_pos == 0 {
+                self.bytes.push(0);
+            }
+            let byte_index = self.bytes.len() - 1;
+            if (value >> i) & 1 == 1 {
+                self.bytes[byte_index] |= 1 << self.bit_pos;
+            }
+            self.bit_pos = (self.bit_pos + 1) % 8;
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Reads values written by [`BitWriter`] back out in the same order.
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    byte_index: usize,
+    bit_pos: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            byte_index: 0,
+            bit_pos: 0,
+        }
+    }
+
+    fn read(&mut self, bits: u32) -> u8 {
+        let mut value = 0u8;
+        for i in 0..bits {
+            let bit = (self.bytes[self.byte_index] >> self.bit_pos) & 1;
+            value |= bit << i;
+            self.bit_pos += 1;
+            if self.bit_pos == 8 {
+                self.bit_pos = 0;
+                self.byte_index += 1;
+            }
+        }
+        value
+    }
+}
+
 impl Chunk {
     pub const WIDTH: usize = 16;
     pub const HALF_WIDTH: usize = Self::WIDTH / 2;
@@ -92,4 +170,123 @@ impl Chunk {
             blocks,
         }
     }
+
+    /// Palette-encodes this chunk for cheap network delivery: a palette of
+    /// the distinct block ids present (plus air), indices bit-packed at
+    /// `ceil(log2(palette_len))` bits, and the vertical air run above each
+    /// column's terrain surface dropped entirely rather than bit-packed,
+    /// since [`Chunk::new`] always fills columns contiguously from `z = 0`.
+    pub fn to_palette_bytes(&self) -> Vec<u8> {
+        let mut palette: Vec<Option<BlockId>> = Vec::new();
+        let mut heights = [0u16; COLUMN_COUNT];
+        let mut column_indices = Vec::with_capacity(Self::SIZE);
+
+        for y in 0..Self::WIDTH {
+            for x in 0..Self::WIDTH {
+                let mut height = 0usize;
+                for z in 0..Self::HEIGHT {
+                    if self.blocks[Self::get_block_index(x, y, z)].is_some() {
+                        height = z + 1;
+                    }
+                }
+                heights[y * Self::WIDTH + x] = height as u16;
+                for z in 0..height {
+                    let value = self.blocks[Self::get_block_index(x, y, z)].map(|b| b.0);
+                    let index = palette
+                        .iter()
+                        .position(|v| *v == value)
+                        .unwrap_or_else(|| {
+                            palette.push(value);
+                            palette.len() - 1
+                        });
+                    column_indices.push(index as u8);
+                }
+            }
+        }
+
+        let bits_per_index = bits_for_palette_len(palette.len());
+        let mut writer = BitWriter::new();
+        for index in &column_indices {
+            writer.write(*index, bits_per_index);
+        }
+        let packed = writer.finish();
+
+        assert!(
+            palette.len() <= u8::MAX as usize,
+            "chunk has more than 255 palette entries (block ids plus air), can't be palette-encoded"
+        );
+        let mut bytes = Vec::with_capacity(2 + palette.len() * 2 + heights.len() * 2 + packed.len());
+        bytes.push(palette.len() as u8);
+        for value in &palette {
+            bytes.push(value.is_some() as u8);
+            bytes.push(value.unwrap_or(0));
+        }
+        for height in &heights {
+            bytes.extend_from_slice(&height.to_le_bytes());
+        }
+        bytes.extend_from_slice(&packed);
+        bytes
+    }
+
+    /// Reconstructs a [`Chunk`] from bytes produced by
+    /// [`Chunk::to_palette_bytes`].
+    pub fn from_palette_bytes(id: ChunkId, bytes: &[u8]) -> Self {
+        let mut cursor = 0usize;
+        let palette_len = bytes[cursor] as usize;
+        cursor += 1;
+        let mut palette = Vec::with_capacity(palette_len);
+        for _ in 0..palette_len {
+            let is_some = bytes[cursor] != 0;
+            let block_id = bytes[cursor + 1];
+            cursor += 2;
+            palette.push(is_some.then_some(block_id));
+        }
+        let mut heights = [0u16; COLUMN_COUNT];
+        for height in heights.iter_mut() {
+            *height = u16::from_le_bytes([bytes[cursor], bytes[cursor + 1]]);
+            cursor += 2;
+        }
+
+        let bits_per_index = bits_for_palette_len(palette_len);
+        let mut reader = BitReader::new(&bytes[cursor..]);
+        let mut blocks = vec![None; Self::SIZE];
+        for y in 0..Self::WIDTH {
+            for x in 0..Self::WIDTH {
+                let height = heights[y * Self::WIDTH + x] as usize;
+                for z in 0..height {
+                    let index = reader.read(bits_per_index) as usize;
+                    let value = palette[index].map(Block);
+                    blocks[Self::get_block_index(x, y, z)] = value;
+                }
+            }
+        }
+        Self { id, blocks }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use noise::Perlin;
+
+    #[test]
+    fn palette_round_trip() {
+        let noise = Perlin::new(1);
+        let id = ChunkId { x: 0, y: 0 };
+        let chunk = Chunk::new(&noise, id);
+        let bytes = chunk.to_palette_bytes();
+        let decoded = Chunk::from_palette_bytes(id, &bytes);
+        assert_eq!(chunk.blocks, decoded.blocks);
+    }
+
+    #[test]
+    #[should_panic(expected = "255")]
+    fn palette_overflow_panics_instead_of_corrupting() {
+        let id = ChunkId { x: 0, y: 0 };
+        let blocks = (0..Chunk::SIZE)
+            .map(|i| Some(Block((i % 256) as u8)))
+            .collect();
+        let chunk = Chunk { id, blocks };
+        chunk.to_palette_bytes();
+    }
 }
@@ -7,9 +7,11 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::chunk::{Chunk, ChunkId};
+use crate::chunk::ChunkId;
 
 pub mod chunk;
+pub mod session;
+pub mod transport;
 
 #[derive(Error, Debug)]
 pub enum SerializeError {
@@ -46,8 +48,28 @@ impl From<ChunkId> for Position {
 pub enum PlayerMessage {
     Message(String),
     UpdatePosition(Position),
-    FetchChunk(ChunkId),
-    FetchPlayers,
+    /// `request_id`, if set, is echoed back on every [`ServerMessage::ChunkPart`]
+    /// answering this fetch, so a caller can correlate the reply without
+    /// filtering the whole broadcast stream.
+    FetchChunk {
+        chunk_id: ChunkId,
+        request_id: Option<u32>,
+    },
+    /// `request_id`, if set, is echoed back on the [`ServerMessage::Players`]
+    /// answering this fetch.
+    FetchPlayers { request_id: Option<u32> },
+    /// Answered with [`ServerMessage::ServerInfo`]. Unlike every other
+    /// variant, this one can be sent (and answered) before a session
+    /// exists at all: in place of a [`session::HandshakeResponse`] during
+    /// the pre-session handshake, so discovery tools and server lists can
+    /// poll liveness and population without opening a full play session.
+    QueryInfo,
+}
+
+/// Bits for [`ServerMessage::ServerInfo`]'s `flags` field.
+pub mod server_info_flags {
+    /// Set if a newly-joined player is replayed recent chat history.
+    pub const HISTORY_REPLAY: u8 = 0b0000_0001;
 }
 
 pub type PlayerId = u16;
@@ -62,11 +84,37 @@ pub struct ChatMessage {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum ServerMessage {
     ChatMessage(ChatMessage),
-    Players(Vec<PlayerId>),
+    /// Answers [`PlayerMessage::FetchPlayers`]; `request_id` echoes the
+    /// request's id, if it had one.
+    Players {
+        players: Vec<PlayerId>,
+        request_id: Option<u32>,
+    },
     PlayerConnected(PlayerId),
     PlayerDisconnected(PlayerId),
     PlayerMoved(PlayerId, Position),
-    Chunk(Chunk),
+    /// One fragment of a palette-encoded [`Chunk`](chunk::Chunk) (see
+    /// [`Chunk::to_palette_bytes`](chunk::Chunk::to_palette_bytes)), sent as a sequence of `total` parts
+    /// numbered from `0` so large chunks don't need a single oversized
+    /// frame. The client reassembles `data` in `part` order once it has
+    /// received all `total` parts for `id`. `request_id` echoes the
+    /// [`PlayerMessage::FetchChunk`] request's id, if it had one, on every
+    /// part.
+    ChunkPart {
+        id: ChunkId,
+        part: u16,
+        total: u16,
+        data: Vec<u8>,
+        request_id: Option<u32>,
+    },
+    /// Answers [`PlayerMessage::QueryInfo`]. `flags` is a bitset of
+    /// [`server_info_flags`].
+    ServerInfo {
+        player_count: u16,
+        max_players: u16,
+        flags: u8,
+        name: String,
+    },
 }
 
 impl TryFrom<ServerMessage> for tungstenite::Message {
@@ -0,0 +1,207 @@
+//! Authenticated handshake and encrypted framing for a player session.
+//!
+//! Every client presents an ed25519 identity key. The server challenges
+//! with a random nonce, the client signs it, and the server derives the
+//! [`PlayerId`] from the identity key, so a client that reconnects with the
+//! same key keeps the same id instead of the ephemeral `addr.port()`
+//! previously used. Once the
+//! handshake is verified, both sides perform an X25519 ECDH using keys
+//! exchanged alongside the signature and wrap all further frames in
+//! ChaCha20-Poly1305 AEAD, keyed by the shared secret and nonced by a
+//! per-direction counter.
+
+use chacha20poly1305::{
+    ChaCha20Poly1305, Key, KeyInit, Nonce,
+    aead::{Aead, Payload},
+};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use x25519_dalek::{EphemeralSecret, PublicKey as ExchangePublicKey, SharedSecret};
+
+use crate::PlayerId;
+
+pub const NONCE_NONCE_LEN: usize = 32;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandshakeChallenge {
+    pub nonce: [u8; NONCE_NONCE_LEN],
+    /// The challenger's own X25519 public key, sent alongside the nonce so
+    /// the handshake completes in a single round trip.
+    pub exchange_key: [u8; 32],
+}
+
+impl HandshakeChallenge {
+    pub fn random(rng: &mut impl RngCore, exchange_key: ExchangePublicKey) -> Self {
+        let mut nonce = [0u8; NONCE_NONCE_LEN];
+        rng.fill_bytes(&mut nonce);
+        Self {
+            nonce,
+            exchange_key: exchange_key.to_bytes(),
+        }
+    }
+
+    pub fn exchange_public_key(&self) -> ExchangePublicKey {
+        ExchangePublicKey::from(self.exchange_key)
+    }
+}
+
+/// Sent by the client in answer to a [`HandshakeChallenge`]: its identity
+/// key, a fresh X25519 key used only for this session's ECDH, and a
+/// signature over the challenge nonce proving ownership of the identity
+/// key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandshakeResponse {
+    pub identity_key: [u8; 32],
+    pub exchange_key: [u8; 32],
+    /// An ed25519 signature is 64 bytes, but `serde`'s derive only covers
+    /// arrays up to 32 elements, so this rides as a `Vec` instead (same as
+    /// [`EncryptedFrame::ciphertext`]).
+    pub signature: Vec<u8>,
+}
+
+#[derive(Error, Debug)]
+pub enum HandshakeError {
+    #[error("malformed identity key")]
+    MalformedIdentityKey,
+    #[error("malformed signature")]
+    MalformedSignature,
+    #[error("signature verification failed")]
+    InvalidSignature,
+}
+
+impl HandshakeResponse {
+    pub fn verify(&self, challenge: &HandshakeChallenge) -> Result<(), HandshakeError> {
+        let verifying_key = VerifyingKey::from_bytes(&self.identity_key)
+            .map_err(|_| HandshakeError::MalformedIdentityKey)?;
+        let signature = Signature::from_slice(&self.signature)
+            .map_err(|_| HandshakeError::MalformedSignature)?;
+        verifying_key
+            .verify(&challenge.nonce, &signature)
+            .map_err(|_| HandshakeError::InvalidSignature)
+    }
+
+    /// Derives a stable [`PlayerId`] from the client's identity key so a
+    /// reconnecting client is recognized as the same player.
+    pub fn player_id(&self) -> PlayerId {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.identity_key.hash(&mut hasher);
+        (hasher.finish() & 0xFFFF) as PlayerId
+    }
+
+    pub fn exchange_public_key(&self) -> ExchangePublicKey {
+        ExchangePublicKey::from(self.exchange_key)
+    }
+}
+
+/// Which side of the connection this endpoint is, used to keep the two
+/// directions' AEAD nonces from ever colliding under the shared key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endpoint {
+    Client,
+    Server,
+}
+
+impl Endpoint {
+    fn peer(self) -> Self {
+        match self {
+            Endpoint::Client => Endpoint::Server,
+            Endpoint::Server => Endpoint::Client,
+        }
+    }
+
+    fn direction_byte(self) -> u8 {
+        match self {
+            Endpoint::Client => 0,
+            Endpoint::Server => 1,
+        }
+    }
+}
+
+fn counter_nonce(direction: Endpoint, counter: u64) -> Nonce {
+    let mut bytes = [0u8; 12];
+    bytes[0] = direction.direction_byte();
+    bytes[4..12].copy_from_slice(&counter.to_be_bytes());
+    *Nonce::from_slice(&bytes)
+}
+
+/// A single encrypted frame on the wire: the sender's counter (so the
+/// receiver can reconstruct the nonce) plus the ciphertext with the
+/// Poly1305 tag appended.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedFrame {
+    pub counter: u64,
+    pub ciphertext: Vec<u8>,
+}
+
+#[derive(Error, Debug)]
+pub enum DecryptError {
+    #[error("frame failed authentication")]
+    AuthenticationFailed,
+    #[error("frame counter {0} was already accepted or is out of order")]
+    Replayed(u64),
+}
+
+/// Wraps a post-handshake connection: encrypts outgoing frames and
+/// authenticates/decrypts incoming ones. A failed [`Self::decrypt`] means
+/// the frame was tampered with or replayed and the connection should be
+/// closed rather than retried.
+#[derive(Clone)]
+pub struct SecureChannel {
+    cipher: ChaCha20Poly1305,
+    endpoint: Endpoint,
+    send_counter: u64,
+    /// The highest peer `counter` accepted so far, so a captured frame
+    /// can't be replayed. Frames must arrive in strictly increasing
+    /// counter order, which holds as long as the transport underneath
+    /// (currently an ordered WebSocket stream) doesn't reorder them.
+    recv_counter: Option<u64>,
+}
+
+impl SecureChannel {
+    pub fn from_shared_secret(shared_secret: SharedSecret, endpoint: Endpoint) -> Self {
+        let key = Key::from_slice(shared_secret.as_bytes());
+        Self {
+            cipher: ChaCha20Poly1305::new(key),
+            endpoint,
+            send_counter: 0,
+            recv_counter: None,
+        }
+    }
+
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> EncryptedFrame {
+        let counter = self.send_counter;
+        self.send_counter += 1;
+        let nonce = counter_nonce(self.endpoint, counter);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, Payload::from(plaintext))
+            .expect("ChaCha20-Poly1305 encryption does not fail");
+        EncryptedFrame { counter, ciphertext }
+    }
+
+    pub fn decrypt(&mut self, frame: &EncryptedFrame) -> Result<Vec<u8>, DecryptError> {
+        if self.recv_counter.is_some_and(|last| frame.counter <= last) {
+            return Err(DecryptError::Replayed(frame.counter));
+        }
+        let nonce = counter_nonce(self.endpoint.peer(), frame.counter);
+        let plaintext = self
+            .cipher
+            .decrypt(&nonce, Payload::from(frame.ciphertext.as_slice()))
+            .map_err(|_| DecryptError::AuthenticationFailed)?;
+        self.recv_counter = Some(frame.counter);
+        Ok(plaintext)
+    }
+}
+
+/// Generates an ephemeral X25519 keypair for either side of the ECDH,
+/// returning the public key to send to the peer (in [`HandshakeChallenge`]
+/// from the server, in [`HandshakeResponse`] from the client) and the
+/// secret to combine with the peer's public key once known.
+pub fn generate_exchange_keypair(rng: impl RngCore + rand::CryptoRng) -> (EphemeralSecret, ExchangePublicKey) {
+    let secret = EphemeralSecret::random_from_rng(rng);
+    let public = ExchangePublicKey::from(&secret);
+    (secret, public)
+}
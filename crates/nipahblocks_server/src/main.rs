@@ -1,34 +1,70 @@
 use anyhow::Result;
 use chrono::Utc;
-use futures_util::StreamExt;
+use futures_util::{SinkExt, StreamExt};
 use log::{error, info};
 use nipahblocks_api::{
     ChatMessage, PlayerId, PlayerMessage, Position, ServerMessage,
     chunk::{Chunk, ChunkId},
+    server_info_flags,
+    session::{self, Endpoint, SecureChannel},
+    transport::{self, Channel, Transport},
 };
 use noise::Perlin;
+use rand::rngs::OsRng;
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     env,
-    sync::Arc,
+    net::SocketAddr,
+    sync::{Arc, Mutex as StdMutex},
 };
 use tokio::{
-    net::{TcpListener, TcpStream},
+    net::{TcpListener, TcpStream, UdpSocket},
     sync::{
         RwLock,
         mpsc::{self, Sender},
     },
 };
 use tokio_stream::wrappers::ReceiverStream;
+use tokio_tungstenite::{WebSocketStream, tungstenite};
+
+mod udp_transport;
+
+use udp_transport::UdpPeerTransport;
 
 const HISTORY_SIZE: usize = 50;
 const PLAYER_CH_SIZE: usize = 100;
 const NOISE_SEED: u32 = 123456;
+/// Max bytes of palette-encoded chunk data carried in one `ChunkPart` frame.
+const CHUNK_PART_SIZE: usize = 1024;
+/// Chebyshev radius (in chunks) within which a player receives another
+/// player's position updates.
+const INTEREST_RADIUS: i16 = 2;
+const SERVER_NAME: &str = "nipahblocks";
+const MAX_PLAYERS: u16 = 256;
+/// Default bind address for the UDP position side, overridden by a second
+/// CLI argument.
+const DEFAULT_UDP_ADDR: &str = "127.0.0.1:8081";
+
+/// All chunks within [`INTEREST_RADIUS`] of `center`, inclusive.
+fn chunks_in_range(center: ChunkId) -> impl Iterator<Item = ChunkId> {
+    (-INTEREST_RADIUS..=INTEREST_RADIUS).flat_map(move |dx| {
+        (-INTEREST_RADIUS..=INTEREST_RADIUS).map(move |dy| ChunkId {
+            x: center.x + dx,
+            y: center.y + dy,
+        })
+    })
+}
 
 struct PlayerState {
     id: PlayerId,
     position: Position,
+    chunk: ChunkId,
     tx: Sender<ServerMessage>,
+    /// Set once the player registers a UDP endpoint (see
+    /// [`transport::hello_packet`]); when present, position updates go out
+    /// this lower-latency, loss-tolerant path instead of the WebSocket
+    /// session.
+    udp_tx: Option<Sender<Vec<u8>>>,
 }
 
 impl PlayerState {
@@ -56,9 +92,12 @@ impl PlayerState {
         self.send_server_message(ServerMessage::PlayerDisconnected(player_id))
             .await;
     }
-    async fn send_player_list(&self, players: Vec<PlayerId>) {
-        self.send_server_message(ServerMessage::Players(players))
-            .await;
+    async fn send_player_list(&self, players: Vec<PlayerId>, request_id: Option<u32>) {
+        self.send_server_message(ServerMessage::Players {
+            players,
+            request_id,
+        })
+        .await;
     }
 
     async fn send_chat_message(&self, message: ChatMessage) {
@@ -67,12 +106,35 @@ impl PlayerState {
     }
 
     async fn send_position_update(&self, player_id: PlayerId, position: Position) {
-        self.send_server_message(ServerMessage::PlayerMoved(player_id, position))
-            .await;
+        let Some(udp_tx) = &self.udp_tx else {
+            self.send_server_message(ServerMessage::PlayerMoved(player_id, position))
+                .await;
+            return;
+        };
+        let payload = bincode::serde::encode_to_vec(
+            ServerMessage::PlayerMoved(player_id, position),
+            bincode::config::standard(),
+        )
+        .expect("ServerMessage encoding is infallible");
+        let _ = udp_tx.send(payload).await;
     }
 
-    async fn send_chunk(&self, chunk: Chunk) {
-        self.send_server_message(ServerMessage::Chunk(chunk)).await;
+    async fn send_chunk_part(
+        &self,
+        id: ChunkId,
+        part: u16,
+        total: u16,
+        data: Vec<u8>,
+        request_id: Option<u32>,
+    ) {
+        self.send_server_message(ServerMessage::ChunkPart {
+            id,
+            part,
+            total,
+            data,
+            request_id,
+        })
+        .await;
     }
 }
 
@@ -80,6 +142,16 @@ struct State {
     history: RwLock<VecDeque<ChatMessage>>,
     players: RwLock<HashMap<PlayerId, PlayerState>>,
     chunks: RwLock<HashMap<ChunkId, Chunk>>,
+    /// Which players are currently in each chunk, kept in sync with
+    /// `players[_].chunk` so broadcasts can be restricted to a radius
+    /// around a chunk without scanning every player.
+    interest: RwLock<HashMap<ChunkId, HashSet<PlayerId>>>,
+    /// Which identity key currently owns each [`PlayerId`], so a second,
+    /// different key landing on the same 16-bit id (an astronomically
+    /// unlikely accident, or a deliberate collision grind) can be told apart
+    /// from the same client legitimately reconnecting. See
+    /// [`State::bind_identity`].
+    identities: RwLock<HashMap<PlayerId, [u8; 32]>>,
     noise: Perlin,
 }
 
@@ -89,10 +161,57 @@ impl State {
             history: RwLock::new(VecDeque::with_capacity(HISTORY_SIZE)),
             players: RwLock::new(HashMap::new()),
             chunks: RwLock::new(HashMap::new()),
+            interest: RwLock::new(HashMap::new()),
+            identities: RwLock::new(HashMap::new()),
             noise: Perlin::new(NOISE_SEED),
         }
     }
 
+    /// Claims `player_id` for `identity_key`, rejecting the claim if a
+    /// different identity key already owns that id. `PlayerId` is only a
+    /// 16-bit hash of the key, so without this check a forged or
+    /// collision-ground key could silently take over another player's id
+    /// (and therefore their chat history and position in the world).
+    async fn bind_identity(&self, player_id: PlayerId, identity_key: [u8; 32]) -> Result<(), ()> {
+        let mut identities = self.identities.write().await;
+        match identities.get(&player_id) {
+            Some(existing) if *existing != identity_key => Err(()),
+            _ => {
+                identities.insert(player_id, identity_key);
+                Ok(())
+            }
+        }
+    }
+
+    async fn join_interest(&self, player_id: PlayerId, chunk_id: ChunkId) {
+        self.interest
+            .write()
+            .await
+            .entry(chunk_id)
+            .or_default()
+            .insert(player_id);
+    }
+
+    async fn leave_interest(&self, player_id: PlayerId, chunk_id: ChunkId) {
+        let mut interest = self.interest.write().await;
+        if let Some(players) = interest.get_mut(&chunk_id) {
+            players.remove(&player_id);
+            if players.is_empty() {
+                interest.remove(&chunk_id);
+            }
+        }
+    }
+
+    /// Ids of players currently within [`INTEREST_RADIUS`] chunks of `center`.
+    async fn players_near(&self, center: ChunkId) -> HashSet<PlayerId> {
+        let interest = self.interest.read().await;
+        chunks_in_range(center)
+            .filter_map(|chunk_id| interest.get(&chunk_id))
+            .flatten()
+            .copied()
+            .collect()
+    }
+
     async fn send_history(&self, player_id: PlayerId) {
         let messages = self.history.read().await.clone();
         self.players.read().await[&player_id]
@@ -100,15 +219,61 @@ impl State {
             .await;
     }
 
-    async fn send_player_connected(&self, player_id: PlayerId) {
-        for (_, player) in self.players.read().await.iter() {
-            player.send_player_connected(player_id).await;
+    /// Answers a [`PlayerMessage::QueryInfo`] from just the player count,
+    /// without needing the requester to have a session at all.
+    async fn server_info(&self) -> ServerMessage {
+        ServerMessage::ServerInfo {
+            player_count: self.players.read().await.len() as u16,
+            max_players: MAX_PLAYERS,
+            flags: server_info_flags::HISTORY_REPLAY,
+            name: SERVER_NAME.to_string(),
         }
     }
 
-    async fn send_player_disconnected(&self, player_id: PlayerId) {
-        for (_, player) in self.players.read().await.iter() {
-            player.send_player_disconnected(player_id).await;
+    /// Tells every player within interest radius of `chunk` that `player_id`
+    /// joined, and tells `player_id` about each of them in turn, so a new
+    /// arrival's remote avatar is only spawned by (and spawns) clients that
+    /// are actually close enough to see it.
+    async fn send_player_connected(&self, player_id: PlayerId, chunk: ChunkId) {
+        let neighbors = self.players_near(chunk).await;
+        let players = self.players.read().await;
+        for peer_id in neighbors.iter().filter(|id| **id != player_id) {
+            if let Some(peer) = players.get(peer_id) {
+                peer.send_player_connected(player_id).await;
+            }
+            if let Some(joiner) = players.get(&player_id) {
+                joiner.send_player_connected(*peer_id).await;
+            }
+        }
+    }
+
+    /// Tells every player within interest radius of `chunk` that `player_id`
+    /// disconnected, mirroring [`State::send_player_connected`].
+    async fn send_player_disconnected(&self, player_id: PlayerId, chunk: ChunkId) {
+        let neighbors = self.players_near(chunk).await;
+        let players = self.players.read().await;
+        for peer_id in neighbors.iter().filter(|id| **id != player_id) {
+            if let Some(peer) = players.get(peer_id) {
+                peer.send_player_disconnected(player_id).await;
+            }
+        }
+    }
+
+    /// Removes `player_id` from `State` and notifies players within
+    /// interest radius of wherever they last were. Shared by the
+    /// WebSocket-close and UDP-shutdown-packet disconnect paths, which can
+    /// race each other — safely idempotent, since a second call finds no
+    /// player left to remove and does nothing.
+    async fn remove_player(&self, player_id: PlayerId) {
+        let last_chunk = self
+            .players
+            .write()
+            .await
+            .remove(&player_id)
+            .map(|player| player.chunk);
+        if let Some(last_chunk) = last_chunk {
+            self.leave_interest(player_id, last_chunk).await;
+            self.send_player_disconnected(player_id, last_chunk).await;
         }
     }
 
@@ -131,23 +296,64 @@ impl State {
     }
 
     async fn update_player_position(&self, player_id: PlayerId, position: Position) {
-        self.players
-            .write()
-            .await
-            .entry(player_id)
-            .and_modify(|player| player.position = position);
-        for player in self.players.read().await.values() {
-            player.send_position_update(player_id, position).await;
+        let new_chunk = ChunkId::from(position);
+        let old_chunk = {
+            let mut players = self.players.write().await;
+            let Some(player) = players.get_mut(&player_id) else {
+                return;
+            };
+            let old_chunk = player.chunk;
+            player.position = position;
+            player.chunk = new_chunk;
+            old_chunk
+        };
+
+        if old_chunk != new_chunk {
+            self.leave_interest(player_id, old_chunk).await;
+            self.join_interest(player_id, new_chunk).await;
+        }
+
+        let neighbors = self.players_near(new_chunk).await;
+
+        if old_chunk != new_chunk {
+            let before = self.players_near(old_chunk).await;
+            let players = self.players.read().await;
+
+            for peer_id in neighbors.difference(&before).filter(|id| **id != player_id) {
+                if let Some(peer) = players.get(peer_id) {
+                    peer.send_player_connected(player_id).await;
+                }
+                if let Some(mover) = players.get(&player_id) {
+                    mover.send_player_connected(*peer_id).await;
+                }
+            }
+            for peer_id in before.difference(&neighbors).filter(|id| **id != player_id) {
+                if let Some(peer) = players.get(peer_id) {
+                    peer.send_player_disconnected(player_id).await;
+                }
+                if let Some(mover) = players.get(&player_id) {
+                    mover.send_player_disconnected(*peer_id).await;
+                }
+            }
+        }
+
+        let players = self.players.read().await;
+        for peer_id in &neighbors {
+            if let Some(peer) = players.get(peer_id) {
+                peer.send_position_update(player_id, position).await;
+            }
         }
     }
 
-    async fn send_player_list(&self, player_id: PlayerId) {
+    async fn send_player_list(&self, player_id: PlayerId, request_id: Option<u32>) {
         let players = self.players.read().await;
         let player_list = players.values().map(|player| player.id).collect();
-        players[&player_id].send_player_list(player_list).await;
+        players[&player_id]
+            .send_player_list(player_list, request_id)
+            .await;
     }
 
-    async fn send_chunk(&self, player_id: PlayerId, chunk_id: ChunkId) {
+    async fn send_chunk(&self, player_id: PlayerId, chunk_id: ChunkId, request_id: Option<u32>) {
         let chunk = self
             .chunks
             .write()
@@ -155,9 +361,15 @@ impl State {
             .entry(chunk_id)
             .or_insert(Chunk::new(&self.noise, chunk_id))
             .clone();
-        self.players.read().await[&player_id]
-            .send_chunk(chunk)
-            .await;
+        let bytes = chunk.to_palette_bytes();
+        let total = bytes.len().div_ceil(CHUNK_PART_SIZE).max(1) as u16;
+        let players = self.players.read().await;
+        let player = &players[&player_id];
+        for (part, data) in (0u16..).zip(bytes.chunks(CHUNK_PART_SIZE)) {
+            player
+                .send_chunk_part(chunk_id, part, total, data.to_vec(), request_id)
+                .await;
+        }
     }
 
     async fn handle_player_message(&self, msg: PlayerMessage, player_id: PlayerId) {
@@ -168,11 +380,20 @@ impl State {
             PlayerMessage::UpdatePosition(pos) => {
                 self.update_player_position(player_id, pos).await;
             }
-            PlayerMessage::FetchPlayers => {
-                self.send_player_list(player_id).await;
+            PlayerMessage::FetchPlayers { request_id } => {
+                self.send_player_list(player_id, request_id).await;
             }
-            PlayerMessage::FetchChunk(chunk_id) => {
-                self.send_chunk(player_id, chunk_id).await;
+            PlayerMessage::FetchChunk {
+                chunk_id,
+                request_id,
+            } => {
+                self.send_chunk(player_id, chunk_id, request_id).await;
+            }
+            PlayerMessage::QueryInfo => {
+                let info = self.server_info().await;
+                self.players.read().await[&player_id]
+                    .send_server_message(info)
+                    .await;
             }
         }
     }
@@ -187,6 +408,14 @@ async fn main() -> Result<()> {
     let listener = TcpListener::bind(&addr).await?;
     info!("Listening on: {addr}");
     let state = Arc::new(State::new());
+
+    let udp_addr = env::args()
+        .nth(2)
+        .unwrap_or_else(|| DEFAULT_UDP_ADDR.to_string());
+    let udp_socket = Arc::new(UdpSocket::bind(&udp_addr).await?);
+    info!("Listening for UDP position traffic on: {udp_addr}");
+    tokio::spawn(udp_listen_loop(udp_socket, state.clone()));
+
     while let Ok((stream, _)) = listener.accept().await {
         let state = state.clone();
         tokio::spawn(accept_connection(stream, state));
@@ -194,50 +423,272 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// Reads [`transport::hello_packet`]s off the shared UDP socket and wires up
+/// each sender's [`UdpPeerTransport`], so a player can opt into receiving
+/// position updates over UDP instead of the WebSocket session. Anything
+/// else arriving here (a stray or malformed datagram) is silently dropped.
+async fn udp_listen_loop(socket: Arc<UdpSocket>, state: Arc<State>) {
+    let mut buf = [0u8; 2048];
+    loop {
+        let (len, from) = match socket.recv_from(&mut buf).await {
+            Ok(pair) => pair,
+            Err(e) => {
+                error!("UDP recv failed: {e}");
+                continue;
+            }
+        };
+        let Ok((packet, _)): std::result::Result<(transport::Packet, usize), _> =
+            bincode::serde::decode_from_slice(&buf[..len], bincode::config::standard())
+        else {
+            continue;
+        };
+        if packet.header.channel != Channel::Control as u8 || packet.payload.len() != 2 {
+            continue;
+        }
+        let player_id = PlayerId::from_le_bytes([packet.payload[0], packet.payload[1]]);
+        register_udp_peer(&state, &socket, player_id, from).await;
+    }
+}
+
+/// Spawns the task that owns a player's [`UdpPeerTransport`] and wires its
+/// outbox into `State`, so position updates for that player from then on go
+/// out over UDP. A hello for a player with no live session
+/// (unknown id, already disconnected) is ignored.
+async fn register_udp_peer(
+    state: &Arc<State>,
+    socket: &Arc<UdpSocket>,
+    player_id: PlayerId,
+    addr: SocketAddr,
+) {
+    let mut players = state.players.write().await;
+    let Some(player) = players.get_mut(&player_id) else {
+        return;
+    };
+    let (outbox_tx, outbox_rx) = mpsc::channel(PLAYER_CH_SIZE);
+    player.udp_tx = Some(outbox_tx);
+    tokio::spawn(udp_peer_task(
+        UdpPeerTransport::new(socket.clone(), addr),
+        outbox_rx,
+        state.clone(),
+        player_id,
+    ));
+    info!("Player {player_id} registered a UDP endpoint at {addr}");
+}
+
+/// Drives one registered peer's UDP side: sends a reliable registration
+/// confirmation up front, retransmits unacked reliable packets on
+/// [`transport::RETRANSMIT_INTERVAL`], relays queued position payloads as
+/// they arrive, and watches for the peer's clean-shutdown control packet so
+/// `player_id` can be removed from `state` deterministically instead of
+/// waiting on the WebSocket to close. Ends when the outbox closes (the
+/// player already disconnected over WebSocket — the peer is told so it can
+/// stop listening), a shutdown packet arrives, or a send fails outright.
+async fn udp_peer_task(
+    mut transport: UdpPeerTransport,
+    mut outbox: mpsc::Receiver<Vec<u8>>,
+    state: Arc<State>,
+    player_id: PlayerId,
+) {
+    if transport
+        .send(Channel::Control, Vec::new(), true)
+        .await
+        .is_err()
+    {
+        return;
+    }
+    let mut ticker = tokio::time::interval(transport::RETRANSMIT_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                if transport.retransmit_due().await.is_err() {
+                    return;
+                }
+            }
+            payload = outbox.recv() => {
+                let Some(payload) = payload else {
+                    let _ = transport.send_shutdown().await;
+                    return;
+                };
+                if transport.send(Channel::Position, payload, false).await.is_err() {
+                    return;
+                }
+            }
+            result = transport.recv() => {
+                match result {
+                    Ok(None) => {
+                        state.remove_player(player_id).await;
+                        return;
+                    }
+                    Ok(Some(_)) => {}
+                    Err(_) => return,
+                }
+            }
+        }
+    }
+}
+
+/// What answering a [`session::HandshakeChallenge`] turned into: either a
+/// verified session, or a bare [`PlayerMessage::QueryInfo`] sent in place of
+/// a [`session::HandshakeResponse`] by a caller that only wants
+/// [`ServerMessage::ServerInfo`] and never intended to join.
+enum HandshakeOutcome {
+    /// The verified identity key is carried alongside the derived
+    /// `PlayerId` so the caller can run [`State::bind_identity`] before
+    /// trusting the id.
+    Session(PlayerId, [u8; 32], SecureChannel),
+    InfoQuery,
+}
+
+/// Runs the pre-session handshake: challenges the client with a nonce,
+/// verifies its signature over that nonce with the claimed identity key,
+/// and derives the shared ChaCha20-Poly1305 key from an X25519 ECDH. The
+/// resulting [`PlayerId`] is stable across reconnects since it's derived
+/// from the client's identity key rather than its ephemeral socket address.
+///
+/// A caller may send [`PlayerMessage::QueryInfo`] instead of the expected
+/// [`session::HandshakeResponse`], in which case this returns
+/// [`HandshakeOutcome::InfoQuery`] without ever deriving a session, so
+/// discovery tools can poll liveness without completing the full handshake.
+async fn perform_handshake(ws_stream: &mut WebSocketStream<TcpStream>) -> Result<HandshakeOutcome> {
+    let (exchange_secret, exchange_public) = session::generate_exchange_keypair(OsRng);
+    let challenge = session::HandshakeChallenge::random(&mut OsRng, exchange_public);
+    let challenge_bytes = bincode::serde::encode_to_vec(&challenge, bincode::config::standard())?;
+    ws_stream
+        .send(tungstenite::Message::Binary(challenge_bytes.into()))
+        .await?;
+
+    let response_msg = ws_stream
+        .next()
+        .await
+        .ok_or_else(|| anyhow::anyhow!("connection closed during handshake"))??;
+    let tungstenite::Message::Binary(data) = response_msg else {
+        anyhow::bail!("handshake response wasn't a binary frame");
+    };
+
+    if let Ok((PlayerMessage::QueryInfo, read)) =
+        bincode::serde::decode_from_slice::<PlayerMessage, _>(&data, bincode::config::standard())
+    {
+        // A `HandshakeResponse` whose identity key happens to start with
+        // `QueryInfo`'s tag byte also decodes here, so only a frame holding
+        // nothing else counts as a query.
+        if read == data.len() {
+            return Ok(HandshakeOutcome::InfoQuery);
+        }
+    }
+
+    let (response, _): (session::HandshakeResponse, usize) =
+        bincode::serde::decode_from_slice(&data, bincode::config::standard())?;
+    response.verify(&challenge)?;
+
+    let shared_secret = exchange_secret.diffie_hellman(&response.exchange_public_key());
+    let secure = SecureChannel::from_shared_secret(shared_secret, Endpoint::Server);
+    Ok(HandshakeOutcome::Session(
+        response.player_id(),
+        response.identity_key,
+        secure,
+    ))
+}
+
+/// Decrypts one inbound WebSocket frame into a [`PlayerMessage`]. A
+/// decryption failure means the frame was tampered with or replayed, so the
+/// caller closes the connection rather than skipping the frame.
+fn decode_secure_frame(
+    secure: &StdMutex<SecureChannel>,
+    msg: tungstenite::Message,
+) -> std::result::Result<PlayerMessage, ()> {
+    let tungstenite::Message::Binary(data) = msg else {
+        return Err(());
+    };
+    let (frame, _): (session::EncryptedFrame, usize) =
+        bincode::serde::decode_from_slice(&data, bincode::config::standard()).map_err(|_| ())?;
+    let plaintext = secure.lock().unwrap().decrypt(&frame).map_err(|_| ())?;
+    let (msg, _) = bincode::serde::decode_from_slice(&plaintext, bincode::config::standard())
+        .map_err(|_| ())?;
+    Ok(msg)
+}
+
 async fn accept_connection(stream: TcpStream, state: Arc<State>) {
     let addr = stream
         .peer_addr()
         .expect("connected streams should have a peer address");
-    let user_id = addr.port();
     info!("Peer address: {}", addr);
-    let ws_stream = tokio_tungstenite::accept_async(stream)
+    let mut ws_stream = tokio_tungstenite::accept_async(stream)
         .await
         .expect("Error during the websocket handshake occurred");
     info!("New WebSocket connection: {}", addr);
+    let (user_id, secure) = match perform_handshake(&mut ws_stream).await {
+        Ok(HandshakeOutcome::Session(user_id, identity_key, secure)) => {
+            if state.bind_identity(user_id, identity_key).await.is_err() {
+                error!(
+                    "Rejected connection from {addr}: identity key doesn't match the existing session for player {user_id}"
+                );
+                return;
+            }
+            (user_id, secure)
+        }
+        Ok(HandshakeOutcome::InfoQuery) => {
+            let info_bytes =
+                bincode::serde::encode_to_vec(state.server_info().await, bincode::config::standard())
+                    .expect("ServerMessage encoding is infallible");
+            let _ = ws_stream
+                .send(tungstenite::Message::Binary(info_bytes.into()))
+                .await;
+            return;
+        }
+        Err(e) => {
+            error!("Handshake with {addr} failed: {e}");
+            return;
+        }
+    };
+    let secure = Arc::new(StdMutex::new(secure));
+
     let (player_tx, player_rx) = mpsc::channel::<ServerMessage>(PLAYER_CH_SIZE);
     let player_rx = ReceiverStream::new(player_rx);
+    let starting_chunk = ChunkId::from(Position::default());
     state.players.write().await.insert(
         user_id,
         PlayerState {
             id: user_id,
             position: Position::default(),
+            chunk: starting_chunk,
             tx: player_tx.clone(),
+            udp_tx: None,
         },
     );
-    state.send_player_connected(user_id).await;
+    state.join_interest(user_id, starting_chunk).await;
+    state.send_player_connected(user_id, starting_chunk).await;
     state.send_history(user_id).await;
     let (write, read) = ws_stream.split();
+    let write_secure = secure.clone();
     let write_task = player_rx
-        .map(|msg| msg.try_into().unwrap())
+        .map(move |msg| {
+            let bytes = bincode::serde::encode_to_vec(&msg, bincode::config::standard())
+                .expect("ServerMessage encoding is infallible");
+            let frame = write_secure.lock().unwrap().encrypt(&bytes);
+            let data = bincode::serde::encode_to_vec(&frame, bincode::config::standard())
+                .expect("EncryptedFrame encoding is infallible");
+            tungstenite::Message::Binary(data.into())
+        })
         .map(Ok)
         .forward(write);
     let read_task = read
-        .filter_map(|msg| async {
+        .filter_map(|msg| async move {
             msg.inspect_err(|e| error!("Failed to read message from user [{user_id}]: {e}"))
                 .ok()
         })
-        .for_each(|msg| {
+        .map(move |msg| decode_secure_frame(&secure, msg))
+        .take_while(|result| std::future::ready(result.is_ok()))
+        .for_each(|result| {
             let state = state.clone();
             async move {
-                let msg: PlayerMessage = msg.try_into().unwrap();
-                state.handle_player_message(msg, user_id).await
+                // `take_while` only lets `Ok` results reach here.
+                state.handle_player_message(result.unwrap(), user_id).await
             }
         });
     tokio::select! {
         _ = read_task => info!("Read task for user {user_id} finished."),
         _ = write_task => info!("Write task for user {user_id} finished."),
     }
-    state.players.write().await.remove(&user_id);
-    state.send_player_disconnected(user_id).await;
+    state.remove_player(user_id).await;
     info!("User {user_id} disconnected.");
 }
@@ -1,12 +1,163 @@
-use futures_util::StreamExt;
-use nipahblocks_api::{PlayerMessage, ServerMessage};
-use std::env;
+use ed25519_dalek::{Signer, SigningKey};
+use futures_util::{SinkExt, StreamExt};
+use nipahblocks_api::{
+    PlayerId, PlayerMessage, ServerMessage,
+    chunk::ChunkId,
+    session::{self, Endpoint, SecureChannel},
+    transport,
+};
+use rand::rngs::OsRng;
+use std::{env, net::SocketAddr, sync::Arc};
 use tokio::{
     io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
-    sync::mpsc::{self, Sender},
+    net::UdpSocket,
+    sync::{
+        Mutex,
+        mpsc::{self, Sender},
+        oneshot,
+    },
+    task::JoinHandle,
 };
 use tokio_stream::wrappers::ReceiverStream;
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use tokio_tungstenite::{
+    MaybeTlsStream, WebSocketStream, connect_async, tungstenite::protocol::Message,
+};
+
+mod rpc;
+mod udp_transport;
+
+use rpc::RpcClient;
+use udp_transport::UdpServerTransport;
+
+/// Default address of the server's UDP position side, used when no second
+/// CLI argument overrides it; must match `nipahblocks_server`'s own default.
+const DEFAULT_UDP_ADDR: &str = "127.0.0.1:8081";
+
+/// Performs the client side of the handshake: signs the server's nonce
+/// with a fresh ed25519 identity key and completes the X25519 ECDH, so the
+/// rest of the session rides on an authenticated, encrypted channel. The
+/// identity key is not stored, so every connection gets a new `PlayerId`. Returns
+/// the derived `PlayerId` alongside the channel since the caller also needs
+/// it to register with the server's UDP side.
+async fn perform_handshake(
+    ws_stream: &mut WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>,
+) -> anyhow::Result<(SecureChannel, PlayerId)> {
+    let challenge_msg = ws_stream
+        .next()
+        .await
+        .ok_or_else(|| anyhow::anyhow!("connection closed during handshake"))??;
+    let Message::Binary(data) = challenge_msg else {
+        anyhow::bail!("handshake challenge wasn't a binary frame");
+    };
+    let (challenge, _): (session::HandshakeChallenge, usize) =
+        bincode::serde::decode_from_slice(&data, bincode::config::standard())?;
+
+    let identity_key = SigningKey::generate(&mut OsRng);
+    let signature = identity_key.sign(&challenge.nonce);
+    let (exchange_secret, exchange_public) = session::generate_exchange_keypair(OsRng);
+    let response = session::HandshakeResponse {
+        identity_key: identity_key.verifying_key().to_bytes(),
+        exchange_key: exchange_public.to_bytes(),
+        signature: signature.to_bytes().to_vec(),
+    };
+    let response_bytes = bincode::serde::encode_to_vec(&response, bincode::config::standard())?;
+    ws_stream
+        .send(Message::Binary(response_bytes.into()))
+        .await?;
+
+    let shared_secret = exchange_secret.diffie_hellman(&challenge.exchange_public_key());
+    Ok((
+        SecureChannel::from_shared_secret(shared_secret, Endpoint::Client),
+        response.player_id(),
+    ))
+}
+
+/// Registers `player_id` with the server's UDP position side, so the
+/// lower-latency path in [`nipahblocks_api::transport`] has a real client
+/// instead of a server-side implementation nothing ever talks to, and
+/// spawns the task that drains it. Returns `None` (UDP position updates
+/// stay disabled, falling back to the WebSocket path) if the socket can't
+/// be set up; otherwise returns a handle the caller uses to ask the task to
+/// send the clean-shutdown packet and wait for it to do so before exiting.
+async fn register_udp_position_feed(
+    player_id: PlayerId,
+    udp_addr: String,
+) -> Option<(oneshot::Sender<()>, JoinHandle<()>)> {
+    let peer: SocketAddr = match udp_addr.parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            eprintln!("UDP position feed disabled: invalid server address {udp_addr}: {e}");
+            return None;
+        }
+    };
+    let socket = match UdpSocket::bind("0.0.0.0:0").await {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("UDP position feed disabled: failed to bind a socket: {e}");
+            return None;
+        }
+    };
+    let hello = transport::hello_packet(player_id);
+    let Ok(bytes) = bincode::serde::encode_to_vec(&hello, bincode::config::standard()) else {
+        return None;
+    };
+    if socket.send_to(&bytes, peer).await.is_err() {
+        return None;
+    }
+
+    let transport = UdpServerTransport::new(Arc::new(socket), peer);
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let handle = tokio::spawn(udp_feed_task(transport, shutdown_rx));
+    Some((shutdown_tx, handle))
+}
+
+/// Drives the client's UDP position feed: transparently handles the
+/// reliable registration-confirmation packet the server sends on a
+/// successful hello (acking it is handled inside [`Transport::recv`]) and
+/// silently discards the [`ServerMessage::PlayerMoved`] payloads it
+/// carries, same as the WebSocket read loop does with the ones it
+/// receives today. Ends, sending the clean-shutdown control packet first,
+/// once told to via `shutdown`.
+async fn udp_feed_task(mut transport: UdpServerTransport, mut shutdown: oneshot::Receiver<()>) {
+    loop {
+        tokio::select! {
+            result = transport.recv() => {
+                match result {
+                    Ok(Some(payload)) => {
+                        let _: Result<(ServerMessage, usize), _> = bincode::serde::decode_from_slice(
+                            &payload,
+                            bincode::config::standard(),
+                        );
+                    }
+                    _ => return,
+                }
+            }
+            _ = &mut shutdown => {
+                let _ = transport.send_shutdown().await;
+                return;
+            }
+        }
+    }
+}
+
+/// Decodes and decrypts one inbound WebSocket frame into a [`ServerMessage`].
+/// Returns `Err(())` for anything malformed (not binary, undecodable, or
+/// failing AEAD authentication) so the caller can close the connection
+/// instead of trusting unauthenticated or corrupt data.
+async fn decode_secure_frame(
+    secure: &Mutex<SecureChannel>,
+    msg: Message,
+) -> std::result::Result<ServerMessage, ()> {
+    let Message::Binary(data) = msg else {
+        return Err(());
+    };
+    let (frame, _): (session::EncryptedFrame, usize) =
+        bincode::serde::decode_from_slice(&data, bincode::config::standard()).map_err(|_| ())?;
+    let plaintext = secure.lock().await.decrypt(&frame).map_err(|_| ())?;
+    let (msg, _) =
+        bincode::serde::decode_from_slice(&plaintext, bincode::config::standard()).map_err(|_| ())?;
+    Ok(msg)
+}
 
 #[tokio::main]
 async fn main() {
@@ -15,42 +166,78 @@ async fn main() {
         .unwrap_or_else(|| panic!("this program requires at least one argument"));
     let (stdin_tx, stdin_rx) = mpsc::channel(30);
     let rx_stream = ReceiverStream::new(stdin_rx);
-    tokio::spawn(read_stdin(stdin_tx));
-    let (ws_stream, _) = connect_async(&url).await.expect("Failed to connect");
+    let rpc = RpcClient::new(stdin_tx.clone());
+    tokio::spawn(read_stdin(stdin_tx, rpc.clone()));
+    let (mut ws_stream, _) = connect_async(&url).await.expect("Failed to connect");
     println!("WebSocket handshake has been successfully completed");
+    let (secure, player_id) = perform_handshake(&mut ws_stream)
+        .await
+        .expect("Failed to complete authenticated session handshake");
+    let secure = Arc::new(Mutex::new(secure));
+    let udp_addr = env::args()
+        .nth(2)
+        .unwrap_or_else(|| DEFAULT_UDP_ADDR.to_string());
+    let udp_feed = register_udp_position_feed(player_id, udp_addr).await;
     let (write, read) = ws_stream.split();
-    let stdin_to_ws = rx_stream.map(Ok).forward(write);
+    let write_secure = secure.clone();
+    let stdin_to_ws = rx_stream
+        .then(move |msg: Message| {
+            let write_secure = write_secure.clone();
+            async move {
+                let Message::Binary(data) = msg else {
+                    return Ok::<_, tokio_tungstenite::tungstenite::Error>(msg);
+                };
+                let frame = write_secure.lock().await.encrypt(&data);
+                let data = bincode::serde::encode_to_vec(&frame, bincode::config::standard())
+                    .expect("EncryptedFrame encoding is infallible");
+                Ok(Message::Binary(data.into()))
+            }
+        })
+        .forward(write);
     let ws_to_stdout = {
-        read.for_each(|message| async {
-            let message: ServerMessage = message.unwrap().try_into().unwrap();
-            match message {
-                ServerMessage::PlayerConnected(user_id) => {
-                    tokio::io::stdout()
-                        .write_all(&format!("user {user_id} joined\n").into_bytes())
-                        .await
-                        .unwrap();
-                }
-                ServerMessage::PlayerDisconnected(user_id) => {
-                    tokio::io::stdout()
-                        .write_all(&format!("user {user_id} left\n").into_bytes())
-                        .await
-                        .unwrap();
+        read.filter_map(|msg| async move {
+            msg.inspect_err(|e| eprintln!("Failed to read message from server: {e}"))
+                .ok()
+        })
+        .then(move |msg| decode_secure_frame(&secure, msg))
+        .take_while(|result| std::future::ready(result.is_ok()))
+        .for_each(|result| {
+            let rpc = rpc.clone();
+            async move {
+                // `take_while` only lets `Ok` results reach here.
+                let message = result.unwrap();
+                if rpc.dispatch(&message).await {
+                    return;
                 }
-                ServerMessage::ChatMessage(msg) => {
-                    tokio::io::stdout()
-                        .write_all(
-                            &format!(
-                                "{} | [{}]: {}\n",
-                                msg.time.format("%d,%H:%M"),
-                                msg.user_id,
-                                msg.content
+                match message {
+                    ServerMessage::PlayerConnected(user_id) => {
+                        tokio::io::stdout()
+                            .write_all(&format!("user {user_id} joined\n").into_bytes())
+                            .await
+                            .unwrap();
+                    }
+                    ServerMessage::PlayerDisconnected(user_id) => {
+                        tokio::io::stdout()
+                            .write_all(&format!("user {user_id} left\n").into_bytes())
+                            .await
+                            .unwrap();
+                    }
+                    ServerMessage::ChatMessage(msg) => {
+                        tokio::io::stdout()
+                            .write_all(
+                                &format!(
+                                    "{} | [{}]: {}\n",
+                                    msg.time.format("%d,%H:%M"),
+                                    msg.user_id,
+                                    msg.content
+                                )
+                                .into_bytes(),
                             )
-                            .into_bytes(),
-                        )
-                        .await
-                        .unwrap();
+                            .await
+                            .unwrap();
+                    }
+                    _ => (),
                 }
-                _ => (),
             }
         })
     };
@@ -58,18 +245,47 @@ async fn main() {
         _ = stdin_to_ws => (),
         _ = ws_to_stdout => (),
     }
+    if let Some((shutdown_tx, handle)) = udp_feed {
+        let _ = shutdown_tx.send(());
+        let _ = handle.await;
+    }
 }
 
 // Our helper method which will read data from stdin and send it along the
-// sender provided.
-async fn read_stdin(tx: Sender<Message>) {
+// sender provided. `/players` and `/chunk <x> <y>` are handled locally via
+// `rpc` instead of being sent as chat; anything else is a chat message.
+async fn read_stdin(tx: Sender<Message>, rpc: RpcClient) {
     let mut br = BufReader::new(tokio::io::stdin());
     let mut line = String::new();
     while let Ok(_) = br.read_line(&mut line).await {
-        let msg = PlayerMessage::Message(line.trim().to_string())
-            .try_into()
-            .unwrap();
-        tx.send(msg).await.expect("Failed to send stdin");
+        let trimmed = line.trim();
+        if trimmed == "/players" {
+            match rpc.fetch_players().await {
+                Ok(players) => println!("players online: {players:?}"),
+                Err(e) => eprintln!("fetch_players failed: {e}"),
+            }
+        } else if let Some(coords) = trimmed.strip_prefix("/chunk ") {
+            match parse_chunk_id(coords) {
+                Some(chunk_id) => match rpc.fetch_chunk(chunk_id).await {
+                    Ok(_) => println!("fetched chunk ({}, {})", chunk_id.x, chunk_id.y),
+                    Err(e) => eprintln!("fetch_chunk failed: {e}"),
+                },
+                None => eprintln!("usage: /chunk <x> <y>"),
+            }
+        } else {
+            let msg = PlayerMessage::Message(trimmed.to_string())
+                .try_into()
+                .unwrap();
+            tx.send(msg).await.expect("Failed to send stdin");
+        }
         line.clear();
     }
 }
+
+fn parse_chunk_id(coords: &str) -> Option<ChunkId> {
+    let (x, y) = coords.split_once(' ')?;
+    Some(ChunkId {
+        x: x.trim().parse().ok()?,
+        y: y.trim().parse().ok()?,
+    })
+}
@@ -0,0 +1,163 @@
+//! Correlates [`PlayerMessage::FetchPlayers`]/[`PlayerMessage::FetchChunk`]
+//! requests with the [`ServerMessage`] replies that echo their
+//! `request_id`, so callers can `.await` a specific reply instead of
+//! filtering the whole broadcast stream, e.g.
+//! `let chunk = client.fetch_chunk(id).await?;`.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use nipahblocks_api::{
+    PlayerId, PlayerMessage, ServerMessage,
+    chunk::{Chunk, ChunkId},
+};
+use thiserror::Error;
+use tokio::{
+    sync::{Mutex, mpsc::Sender, oneshot},
+    time,
+};
+use tokio_tungstenite::tungstenite::protocol::Message;
+
+const RPC_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Error, Debug)]
+pub enum RpcError {
+    #[error("no reply received within {RPC_TIMEOUT:?}")]
+    Timeout,
+    #[error("the connection closed before a reply arrived")]
+    ConnectionClosed,
+}
+
+struct PendingChunk {
+    reply: oneshot::Sender<Chunk>,
+    parts: Vec<Option<Vec<u8>>>,
+}
+
+#[derive(Clone)]
+pub struct RpcClient {
+    tx: Sender<Message>,
+    next_id: Arc<Mutex<u32>>,
+    pending_players: Arc<Mutex<HashMap<u32, oneshot::Sender<Vec<PlayerId>>>>>,
+    pending_chunks: Arc<Mutex<HashMap<u32, PendingChunk>>>,
+}
+
+impl RpcClient {
+    /// `tx` is the same outbound channel the rest of the client uses to
+    /// reach the WebSocket write task, so RPC requests are framed and
+    /// encrypted identically to any other outgoing message.
+    pub fn new(tx: Sender<Message>) -> Self {
+        Self {
+            tx,
+            next_id: Arc::new(Mutex::new(0)),
+            pending_players: Arc::new(Mutex::new(HashMap::new())),
+            pending_chunks: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    async fn next_request_id(&self) -> u32 {
+        let mut next_id = self.next_id.lock().await;
+        let id = *next_id;
+        *next_id = next_id.wrapping_add(1);
+        id
+    }
+
+    async fn send(&self, msg: PlayerMessage) {
+        let encoded: Message = msg
+            .try_into()
+            .expect("PlayerMessage encoding is infallible");
+        let _ = self.tx.send(encoded).await;
+    }
+
+    pub async fn fetch_players(&self) -> Result<Vec<PlayerId>, RpcError> {
+        let request_id = self.next_request_id().await;
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.pending_players
+            .lock()
+            .await
+            .insert(request_id, reply_tx);
+        self.send(PlayerMessage::FetchPlayers {
+            request_id: Some(request_id),
+        })
+        .await;
+        match time::timeout(RPC_TIMEOUT, reply_rx).await {
+            Ok(Ok(players)) => Ok(players),
+            Ok(Err(_)) => Err(RpcError::ConnectionClosed),
+            Err(_) => {
+                self.pending_players.lock().await.remove(&request_id);
+                Err(RpcError::Timeout)
+            }
+        }
+    }
+
+    pub async fn fetch_chunk(&self, chunk_id: ChunkId) -> Result<Chunk, RpcError> {
+        let request_id = self.next_request_id().await;
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.pending_chunks.lock().await.insert(
+            request_id,
+            PendingChunk {
+                reply: reply_tx,
+                parts: Vec::new(),
+            },
+        );
+        self.send(PlayerMessage::FetchChunk {
+            chunk_id,
+            request_id: Some(request_id),
+        })
+        .await;
+        match time::timeout(RPC_TIMEOUT, reply_rx).await {
+            Ok(Ok(chunk)) => Ok(chunk),
+            Ok(Err(_)) => Err(RpcError::ConnectionClosed),
+            Err(_) => {
+                self.pending_chunks.lock().await.remove(&request_id);
+                Err(RpcError::Timeout)
+            }
+        }
+    }
+
+    /// Feeds an incoming [`ServerMessage`] to the client. Returns `true` if
+    /// it was a reply this client was waiting on (and has been consumed),
+    /// `false` if the caller should handle it itself.
+    pub async fn dispatch(&self, msg: &ServerMessage) -> bool {
+        match msg {
+            ServerMessage::Players {
+                players,
+                request_id: Some(request_id),
+            } => {
+                if let Some(reply) = self.pending_players.lock().await.remove(request_id) {
+                    let _ = reply.send(players.clone());
+                    return true;
+                }
+                false
+            }
+            ServerMessage::ChunkPart {
+                id,
+                part,
+                total,
+                data,
+                request_id: Some(request_id),
+            } => {
+                let mut pending = self.pending_chunks.lock().await;
+                let Some(entry) = pending.get_mut(request_id) else {
+                    return false;
+                };
+                if entry.parts.is_empty() {
+                    entry.parts = vec![None; *total as usize];
+                }
+                let Some(slot) = entry.parts.get_mut(*part as usize) else {
+                    return false;
+                };
+                *slot = Some(data.clone());
+                if entry.parts.iter().all(Option::is_some) {
+                    let entry = pending.remove(request_id).unwrap();
+                    let bytes: Vec<u8> = entry
+                        .parts
+                        .into_iter()
+                        .flat_map(|part| part.unwrap())
+                        .collect();
+                    let _ = entry.reply.send(Chunk::from_palette_bytes(*id, &bytes));
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! UDP implementation of [`Transport`] for the client side of the server's
+//! UDP position feed, mirroring `nipahblocks_server`'s `udp_transport`
+//! module since the protocol is symmetric: each side just sees one peer.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use nipahblocks_api::transport::{self, Channel, Packet, PeerReliability, Transport};
+use tokio::net::UdpSocket;
+
+/// The client's UDP session with the server: a handle to its own socket,
+/// the server's address, and the reliability bookkeeping for that peer.
+pub struct UdpServerTransport {
+    socket: Arc<UdpSocket>,
+    peer: SocketAddr,
+    reliability: PeerReliability,
+}
+
+impl UdpServerTransport {
+    pub fn new(socket: Arc<UdpSocket>, peer: SocketAddr) -> Self {
+        Self {
+            socket,
+            peer,
+            reliability: PeerReliability::new(),
+        }
+    }
+
+    async fn send_packet(&self, packet: &Packet) -> std::io::Result<()> {
+        let bytes = bincode::serde::encode_to_vec(packet, bincode::config::standard())
+            .expect("packet encoding is infallible");
+        self.socket.send_to(&bytes, self.peer).await?;
+        Ok(())
+    }
+
+    /// Retransmits any reliable packets past their retransmit deadline.
+    /// Should be driven on a `tokio::time::interval(transport::RETRANSMIT_INTERVAL)`.
+    pub async fn retransmit_due(&mut self) -> std::io::Result<()> {
+        for packet in self.reliability.due_for_retransmit() {
+            self.send_packet(&packet).await?;
+        }
+        Ok(())
+    }
+
+    /// Sends the clean-shutdown control packet so the server can remove
+    /// this player deterministically instead of waiting for the WebSocket
+    /// to close.
+    pub async fn send_shutdown(&self) -> std::io::Result<()> {
+        self.send_packet(&transport::shutdown_packet()).await
+    }
+}
+
+impl Transport for UdpServerTransport {
+    type Error = std::io::Error;
+
+    async fn send(
+        &mut self,
+        channel: Channel,
+        payload: Vec<u8>,
+        reliable: bool,
+    ) -> Result<(), Self::Error> {
+        let packet = self.reliability.prepare_send(channel, payload, reliable);
+        self.send_packet(&packet).await
+    }
+
+    async fn recv(&mut self) -> Result<Option<Vec<u8>>, Self::Error> {
+        let mut buf = [0u8; 2048];
+        loop {
+            let (len, from) = self.socket.recv_from(&mut buf).await?;
+            if from != self.peer {
+                continue;
+            }
+            let (packet, _): (Packet, usize) =
+                match bincode::serde::decode_from_slice(&buf[..len], bincode::config::standard())
+                {
+                    Ok(decoded) => decoded,
+                    Err(_) => continue,
+                };
+            if packet.is_shutdown() {
+                return Ok(None);
+            }
+            let (payload, ack) = self.reliability.receive(packet);
+            if let Some(ack) = ack {
+                self.send_packet(&ack).await?;
+            }
+            if let Some(payload) = payload {
+                return Ok(Some(payload));
+            }
+        }
+    }
+}